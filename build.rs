@@ -10,6 +10,7 @@ fn main() {
     //     .compile("tid");
     println!("cargo:rustc-link-lib=framework=Foundation");
     println!("cargo:rustc-link-lib=framework=LocalAuthentication");
+    println!("cargo:rustc-link-lib=framework=Security");
     println!("cargo:rerun-if-changed=foreign/tid.m");
     println!("cargo:rerun-if-changed=foreign/tid.h");
 
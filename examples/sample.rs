@@ -1,11 +1,14 @@
 use tid::{LAContext, LAPolicy};
 
-#[tokio::main(flavor = "current_thread")]
+#[tokio::main]
 async fn main() {
     let mut ctx = LAContext::new();
 
     ctx.set_localized_cancel_title("Use Another Method");
-    if ctx.can_evaluate_policy(LAPolicy::DeviceOwnerAuthenticationWithBiometrics) {
+    if ctx
+        .can_evaluate_policy(LAPolicy::DeviceOwnerAuthenticationWithBiometrics)
+        .is_ok()
+    {
         println!("device supports biometrics authentication");
         let auth_result = ctx.evaluate_policy(
             LAPolicy::DeviceOwnerAuthenticationWithBiometrics,
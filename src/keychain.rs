@@ -0,0 +1,212 @@
+//! Keychain-backed secret storage gated by biometric access control.
+//!
+//! Wraps `SecItemAdd`/`SecItemCopyMatching` so a secret can only be read
+//! back after a successful Touch ID/Face ID evaluation against a given
+//! [`LAContext`], via a `SecAccessControl` built with
+//! `SecAccessControlCreateWithFlags`.
+
+use crate::LAContext;
+use std::ffi::{c_void, CString};
+use std::os::raw::c_char;
+
+extern "C" {
+    fn keychain_set_item(
+        service: *const c_char,
+        account: *const c_char,
+        data: *const u8,
+        data_len: usize,
+        access_control_flags: u32,
+        la_context: *mut c_void,
+    ) -> i32;
+    fn keychain_get_item(
+        service: *const c_char,
+        account: *const c_char,
+        la_context: *mut c_void,
+        out_ptr: *mut *const u8,
+        out_len: *mut usize,
+    ) -> i32;
+    fn free_bytes(ptr: *const u8);
+}
+
+/// Flags controlling how a Keychain item's access control is constructed,
+/// mirroring `SecAccessControlCreateFlags`. Combine with `|`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct AccessControlFlags(u32);
+
+impl AccessControlFlags {
+    /// Require user presence (passcode or biometrics) at access time.
+    pub const USER_PRESENCE: AccessControlFlags = AccessControlFlags(1 << 0);
+    /// Require a biometric match using any enrolled finger/face.
+    pub const BIOMETRY_ANY: AccessControlFlags = AccessControlFlags(1 << 1);
+    /// Require a biometric match against the set of biometrics enrolled
+    /// *right now*; invalidated the moment enrollment changes, which is the
+    /// usual choice for protecting a secret against an attacker who enrolls
+    /// their own biometrics.
+    pub const BIOMETRY_CURRENT_SET: AccessControlFlags = AccessControlFlags(1 << 3);
+    /// Require the device passcode.
+    pub const DEVICE_PASSCODE: AccessControlFlags = AccessControlFlags(1 << 4);
+
+    fn bits(self) -> u32 {
+        self.0
+    }
+}
+
+impl std::ops::BitOr for AccessControlFlags {
+    type Output = AccessControlFlags;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        AccessControlFlags(self.0 | rhs.0)
+    }
+}
+
+/// Error returned by a [`BiometricItem`] operation, wrapping the Keychain's
+/// `OSStatus` result code.
+///
+/// Only the codes a caller is realistically expected to branch on are
+/// named; anything else is surfaced as [`SecError::Other`] with the raw
+/// status, since `OSStatus` is an open-ended space rather than a small
+/// closed enum like [`crate::LAError`].
+#[derive(Copy, Clone, Debug, thiserror::Error)]
+pub enum SecError {
+    /// No item matching the query was found.
+    #[error("No item matching the query was found.")]
+    ItemNotFound,
+    /// An item with the same primary key already exists.
+    #[error("An item with the same primary key already exists.")]
+    DuplicateItem,
+    /// Biometric or passcode authentication failed.
+    #[error("Biometric or passcode authentication failed.")]
+    AuthFailed,
+    /// User interaction was required to satisfy the item's access control
+    /// but was disallowed or not possible.
+    #[error("User interaction is required but was disallowed or not possible.")]
+    InteractionNotAllowed,
+    /// The user canceled the authentication prompt.
+    #[error("The user canceled the operation.")]
+    UserCanceled,
+    /// `service` or `account` contained an interior NUL byte, so it couldn't
+    /// be passed to the Keychain APIs, which expect a NUL-terminated C
+    /// string.
+    #[error("service/account must not contain a NUL byte.")]
+    InvalidIdentifier,
+    /// Any other `OSStatus` returned by the Keychain.
+    #[error("Keychain operation failed with OSStatus {0}.")]
+    Other(i32),
+}
+
+impl SecError {
+    fn from_status(status: i32) -> Self {
+        match status {
+            -25300 => SecError::ItemNotFound,
+            -25299 => SecError::DuplicateItem,
+            -25293 => SecError::AuthFailed,
+            -25308 => SecError::InteractionNotAllowed,
+            -128 => SecError::UserCanceled,
+            other => SecError::Other(other),
+        }
+    }
+}
+
+/// A Keychain-backed secret gated by biometric (or passcode) access control.
+///
+/// ## Overview
+/// [`BiometricItem::set`] stores a generic-password Keychain item protected
+/// by a `SecAccessControl`, so the item can only be read back after the
+/// holder authenticates against a given [`LAContext`] with
+/// [`BiometricItem::get`].
+///
+/// To avoid re-prompting on back-to-back reads, set
+/// [`LAContext::set_touch_id_authentication_allowable_reuse_duration`] on
+/// the context passed to `get` before the first read.
+pub struct BiometricItem;
+
+impl BiometricItem {
+    /// Stores `data` under `service`/`account`, protected by `flags`.
+    ///
+    /// Replaces any existing item with the same service/account. The add is
+    /// attempted first so a failing call leaves the previous item in place;
+    /// only a pre-existing item is deleted, and only once the add reports
+    /// it's a duplicate. This isn't atomic: if the delete succeeds but the
+    /// retried add then fails (e.g. `flags` can't be satisfied for this
+    /// item), the old secret is gone and the new one was never stored.
+    pub fn set(
+        service: &str,
+        account: &str,
+        data: &[u8],
+        flags: AccessControlFlags,
+        context: &LAContext,
+    ) -> Result<(), SecError> {
+        let service = CString::new(service).map_err(|_| SecError::InvalidIdentifier)?;
+        let account = CString::new(account).map_err(|_| SecError::InvalidIdentifier)?;
+        let status = unsafe {
+            keychain_set_item(
+                service.as_ptr(),
+                account.as_ptr(),
+                data.as_ptr(),
+                data.len(),
+                flags.bits(),
+                context.inner,
+            )
+        };
+        if status == 0 {
+            Ok(())
+        } else {
+            Err(SecError::from_status(status))
+        }
+    }
+
+    /// Reads back the secret stored under `service`/`account`, prompting for
+    /// biometric or passcode authentication via `context` if required by the
+    /// item's access control.
+    pub fn get(service: &str, account: &str, context: &LAContext) -> Result<Vec<u8>, SecError> {
+        let service = CString::new(service).map_err(|_| SecError::InvalidIdentifier)?;
+        let account = CString::new(account).map_err(|_| SecError::InvalidIdentifier)?;
+        let mut ptr: *const u8 = std::ptr::null();
+        let mut len: usize = 0;
+        let status = unsafe {
+            keychain_get_item(
+                service.as_ptr(),
+                account.as_ptr(),
+                context.inner,
+                &mut ptr,
+                &mut len,
+            )
+        };
+        if status != 0 {
+            return Err(SecError::from_status(status));
+        }
+        let bytes = unsafe { std::slice::from_raw_parts(ptr, len).to_vec() };
+        unsafe {
+            free_bytes(ptr);
+        }
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_status_maps_named_codes() {
+        assert!(matches!(SecError::from_status(-25300), SecError::ItemNotFound));
+        assert!(matches!(SecError::from_status(-25299), SecError::DuplicateItem));
+        assert!(matches!(SecError::from_status(-25293), SecError::AuthFailed));
+        assert!(matches!(
+            SecError::from_status(-25308),
+            SecError::InteractionNotAllowed
+        ));
+        assert!(matches!(SecError::from_status(-128), SecError::UserCanceled));
+    }
+
+    #[test]
+    fn from_status_falls_back_to_other() {
+        assert!(matches!(SecError::from_status(-50), SecError::Other(-50)));
+    }
+
+    #[test]
+    fn access_control_flags_combine_bits() {
+        let combined = AccessControlFlags::USER_PRESENCE | AccessControlFlags::BIOMETRY_CURRENT_SET;
+        assert_eq!(combined.bits(), (1 << 0) | (1 << 3));
+    }
+}
@@ -13,12 +13,12 @@
 //! ```rust
 //! use tid::{LAContext, LAPolicy};
 //!
-//! #[tokio::main(flavor = "current_thread")]
+//! #[tokio::main]
 //! async fn main() {
 //!     let mut ctx = LAContext::new();
 //!
 //!     ctx.set_localized_cancel_title("Use Another Method");
-//!     if ctx.can_evaluate_policy(LAPolicy::DeviceOwnerAuthenticationWithBiometrics) {
+//!     if ctx.can_evaluate_policy(LAPolicy::DeviceOwnerAuthenticationWithBiometrics).is_ok() {
 //!         println!("device supports biometrics authentication");
 //!         let auth_result = ctx.evaluate_policy(
 //!             LAPolicy::DeviceOwnerAuthenticationWithBiometrics,
@@ -30,21 +30,23 @@
 //! ```
 #![deny(missing_docs)]
 
+pub mod keychain;
+
 use num::FromPrimitive;
 use parking_lot::Mutex;
-use std::cell::Cell;
 use std::ffi::{c_void, CString};
 use std::future::Future;
 use std::os::raw::c_char;
 use std::pin::Pin;
-use std::rc::Rc;
+use std::sync::Arc;
 use std::task::{Context, Poll, Waker};
 
 extern "C" {
     fn create_la_context() -> *mut c_void;
     fn drop_la_context(ctx: *mut c_void);
     fn set_localized_cancel_title(ctx: *mut c_void, reason: *const c_char);
-    fn can_evaluate_policy(ctx: *mut c_void, policy: i32) -> i32;
+    fn set_localized_fallback_title(ctx: *mut c_void, title: *const c_char);
+    fn can_evaluate_policy(ctx: *mut c_void, policy: i32, error: *mut i32) -> i32;
     fn evaluate_policy(
         ctx: *mut c_void,
         policy: i32,
@@ -52,6 +54,15 @@ extern "C" {
         user_data: *const c_void,
         callback: *mut c_void,
     );
+    fn biometry_type(ctx: *mut c_void) -> i32;
+    fn evaluated_policy_domain_state(
+        ctx: *mut c_void,
+        out_ptr: *mut *const u8,
+        out_len: *mut usize,
+    ) -> i32;
+    fn free_bytes(ptr: *const u8);
+    fn invalidate(ctx: *mut c_void);
+    fn set_touch_id_authentication_allowable_reuse_duration(ctx: *mut c_void, duration: f64);
 }
 
 /// Binding to `LAPolicy` of `LocalAuthentication`
@@ -72,6 +83,25 @@ pub enum LAPolicy {
     DeviceOwnerAuthenticationWithWristDetection = 5,
 }
 
+/// Binding to `LABiometryType` of `LocalAuthentication`
+///
+/// The biometry type that the device supports.
+#[repr(i32)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, num_derive::FromPrimitive)]
+pub enum LABiometryType {
+    /// No biometry is available on the device.
+    None = 0,
+    /// The device supports Touch ID.
+    TouchID = 1,
+    /// The device supports Face ID.
+    FaceID = 2,
+    /// The device supports Optic ID.
+    OpticID = 4,
+    /// The framework returned a biometry type this crate doesn't recognize
+    /// (e.g. a future OS adds one).
+    Unknown = i32::MIN,
+}
+
 /// Binding to `LAError` of `LocalAuthentication`
 ///
 /// Error codes that the framework returns when policy evaluation fails.
@@ -123,6 +153,9 @@ pub enum LAError {
     /// An attempt to authenticate with Apple Watch failed.
     #[error("An attempt to authenticate with Apple Watch failed.")]
     LAErrorWatchNotAvailable = -11,
+    /// The framework returned an error code this crate doesn't recognize.
+    #[error("An unrecognized LAError code was returned by the framework.")]
+    LAErrorUnknown = i32::MIN,
 }
 
 /// Binding to `LAContext` of `LocalAuthentication`
@@ -144,7 +177,7 @@ pub enum LAError {
 /// if your app allows biometric authentication. Otherwise, authorization requests may fail.
 ///
 pub struct LAContext {
-    inner: *mut c_void,
+    pub(crate) inner: *mut c_void,
 }
 
 impl LAContext {
@@ -177,20 +210,92 @@ impl LAContext {
         }
     }
 
+    /// set `localizedFallbackTitle` property.
+    ///
+    /// The localized title for the fallback button in the dialog presented to the user during authentication.
+    ///
+    /// ### Discussion
+    /// The system presents a fallback button during biometric authentication
+    /// when the task also allows passcode authentication, giving the user an
+    /// escape hatch (e.g. "Use Password…") if biometrics fail or aren't available.
+    /// If the user taps it and no fallback exists for the chosen policy,
+    /// [`evaluate_policy`](LAContext::evaluate_policy) resolves with
+    /// [`LAError::LAErrorUserFallback`], which is a good place to retry with
+    /// a policy that accepts the device passcode.
+    ///
+    /// Use the localizedFallbackTitle property to choose a title for the fallback button.
+    /// If you set the property to nil—as it is by default—the system uses an
+    /// appropriate default title. Assign an empty string to hide the button entirely.
+    pub fn set_localized_fallback_title(&mut self, title: &str) {
+        let title = CString::new(title).unwrap();
+        unsafe {
+            set_localized_fallback_title(self.inner, title.as_ptr());
+        }
+    }
+
+    /// set `touchIDAuthenticationAllowableReuseDuration` property.
+    ///
+    /// The duration, in seconds, for which a successful Touch ID
+    /// authentication is reused before the system prompts again.
+    ///
+    /// ### Discussion
+    /// This is most useful paired with [`keychain::BiometricItem::get`]:
+    /// set it before a read so a second read within `duration` seconds
+    /// reuses the earlier successful authentication instead of re-prompting.
+    /// Pass `0.0` to disable reuse, which is the default.
+    pub fn set_touch_id_authentication_allowable_reuse_duration(&mut self, duration: f64) {
+        unsafe {
+            set_touch_id_authentication_allowable_reuse_duration(self.inner, duration);
+        }
+    }
+
     /// Assesses whether authentication can proceed for a given policy.
-    pub fn can_evaluate_policy(&self, policy: LAPolicy) -> bool {
-        unsafe { can_evaluate_policy(self.inner, policy as i32) == 1 }
+    ///
+    /// Unlike a plain bool, the error tells you *why* evaluation isn't
+    /// possible right now, e.g. [`LAError::LAErrorBiometryNotAvailable`]
+    /// (no biometric hardware, so fall back silently) versus
+    /// [`LAError::LAErrorBiometryNotEnrolled`] (hardware is present but
+    /// nothing is enrolled, so prompt the user to set it up) versus
+    /// [`LAError::LAErrorPasscodeNotSet`].
+    pub fn can_evaluate_policy(&self, policy: LAPolicy) -> Result<(), LAError> {
+        let mut error_code = 0i32;
+        let can_evaluate =
+            unsafe { can_evaluate_policy(self.inner, policy as i32, &mut error_code) == 1 };
+        if can_evaluate {
+            Ok(())
+        } else {
+            Err(FromPrimitive::from_i32(error_code).unwrap_or(LAError::LAErrorUnknown))
+        }
+    }
+
+    /// Returns the type of biometry supported by the device.
+    ///
+    /// ### Important
+    /// This value is only meaningful after [`can_evaluate_policy`](LAContext::can_evaluate_policy)
+    /// has been called at least once: `LAContext` only populates `biometryType`
+    /// as a side effect of evaluating whether biometric authentication is possible.
+    /// Call `can_evaluate_policy` first, then use this to tailor the prompt
+    /// (e.g. "Unlock using Face ID" vs. "Unlock using Touch ID").
+    pub fn biometry_type(&self) -> LABiometryType {
+        let raw = unsafe { biometry_type(self.inner) };
+        FromPrimitive::from_i32(raw).unwrap_or(LABiometryType::Unknown)
     }
 
     /// Evaluates the specified policy.
-    pub async fn evaluate_policy(
+    ///
+    /// This is a plain (non-`async`) fn returning `impl Future` rather than
+    /// an `async fn` so that it doesn't need to hold `&self` across the
+    /// await point: `LAContext` wraps a raw pointer and isn't `Sync`, so an
+    /// `async fn` signature would make the returned future `!Send` even
+    /// though [`EvaluateFuture`] itself is `Send`. Everything involving
+    /// `self` runs synchronously before the future is constructed and
+    /// returned.
+    pub fn evaluate_policy(
         &self,
         policy: LAPolicy,
         localized_reason: &str,
-    ) -> Result<(), LAError> {
+    ) -> impl Future<Output = Result<(), LAError>> + Send {
         let reason = CString::new(localized_reason).unwrap();
-        // let (tx, rx) = tokio::sync::oneshot::channel();
-        // let tx = Box::into_raw(Box::new(tx));
         let fut = EvaluateFuture::new();
         unsafe {
             evaluate_policy(
@@ -201,7 +306,49 @@ impl LAContext {
                 evaluate_callback as *mut c_void,
             );
         }
-        fut.await
+        fut
+    }
+
+    /// Returns `evaluatedPolicyDomainState`, an opaque blob that stays stable
+    /// until the device's enrolled biometric database changes (a fingerprint
+    /// or face is added or removed).
+    ///
+    /// ### Important
+    /// This value is only populated after a successful
+    /// [`evaluate_policy`](LAContext::evaluate_policy) call; it returns `None`
+    /// beforehand. Persist the blob alongside a secret you protect with
+    /// biometrics, and compare it to the stored value on the next launch to
+    /// decide whether enrollment has changed and cached credentials should be
+    /// invalidated.
+    pub fn evaluated_policy_domain_state(&self) -> Option<Vec<u8>> {
+        let mut ptr: *const u8 = std::ptr::null();
+        let mut len: usize = 0;
+        let has_state =
+            unsafe { evaluated_policy_domain_state(self.inner, &mut ptr, &mut len) == 1 };
+        if !has_state {
+            return None;
+        }
+        let bytes = unsafe { std::slice::from_raw_parts(ptr, len).to_vec() };
+        unsafe {
+            free_bytes(ptr);
+        }
+        Some(bytes)
+    }
+
+    /// Invalidates the context, cancelling any in-flight authentication.
+    ///
+    /// ### Discussion
+    /// If an [`evaluate_policy`](LAContext::evaluate_policy) call is pending
+    /// when this is invoked, the system immediately dismisses the
+    /// authentication UI and the pending future completes with
+    /// [`LAError::LAErrorSystemCancel`] or [`LAError::LAErrorAppCancel`].
+    /// This is useful for timeout patterns, e.g. aborting a prompt when the
+    /// app backgrounds. After invalidation the context can't be reused; drop
+    /// it and create a new one for any further authentication.
+    pub fn invalidate(&self) {
+        unsafe {
+            invalidate(self.inner);
+        }
     }
 }
 
@@ -214,26 +361,31 @@ impl Drop for LAContext {
 }
 
 struct EvaluateFuture {
-    inner: Rc<EvaluateFutureInner>,
+    inner: Arc<EvaluateFutureInner>,
+}
+
+#[derive(Default)]
+struct EvaluateFutureState {
+    result: Option<Result<(), LAError>>,
+    waker: Option<Waker>,
 }
 
 #[derive(Default)]
 struct EvaluateFutureInner {
-    result: Mutex<Cell<Option<Result<(), LAError>>>>,
-    waker: Cell<Option<Waker>>,
+    state: Mutex<EvaluateFutureState>,
 }
 
 impl EvaluateFuture {
     fn new() -> EvaluateFuture {
         EvaluateFuture {
-            inner: Rc::new(EvaluateFutureInner::default()),
+            inner: Arc::new(EvaluateFutureInner::default()),
         }
     }
 }
 
 impl EvaluateFutureInner {
-    fn into_raw(self: Rc<Self>) -> *const EvaluateFutureInner {
-        Rc::into_raw(self)
+    fn into_raw(self: Arc<Self>) -> *const EvaluateFutureInner {
+        Arc::into_raw(self)
     }
 }
 
@@ -241,31 +393,118 @@ impl Future for EvaluateFuture {
     type Output = Result<(), LAError>;
 
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        let guard = self.inner.result.lock();
-        self.inner.waker.set(Some(cx.waker().clone()));
-        if let Some(result) = guard.take() {
+        let mut state = self.inner.state.lock();
+        if let Some(result) = state.result.take() {
             Poll::Ready(result)
         } else {
+            state.waker = Some(cx.waker().clone());
             Poll::Pending
         }
     }
 }
 
+// The Objective-C reply block hops to a background queue and may run before
+// `EvaluateFuture` is ever polled, so the waker can't be assumed to be set
+// yet: stash the result under the lock first, then wake whatever waker (if
+// any) is there. This also means no spin-loop is needed — there is nothing
+// to wait for.
 unsafe extern "C" fn evaluate_callback(tx: *const c_void, success: i32, code: i32) {
-    let fut = Rc::from_raw(tx as *const EvaluateFutureInner);
-    let guard = fut.result.lock();
-    if success == 1 {
-        guard.set(Some(Ok(())));
+    let fut = Arc::from_raw(tx as *const EvaluateFutureInner);
+    let result = if success == 1 {
+        Ok(())
     } else {
-        let error: LAError = FromPrimitive::from_i32(code).unwrap();
-        guard.set(Some(Err(error)));
+        Err(FromPrimitive::from_i32(code).unwrap_or(LAError::LAErrorUnknown))
+    };
+    let waker = {
+        let mut state = fut.state.lock();
+        state.result = Some(result);
+        state.waker.take()
+    };
+    if let Some(waker) = waker {
+        waker.wake();
+    }
+}
+
+// Compile-time check that `EvaluateFuture` stays `Send`: a future refactor
+// that reintroduces `Rc`/`Cell` into `EvaluateFutureInner` would silently
+// bring back the bug this request fixed, so make it a build failure instead.
+#[allow(dead_code)]
+fn assert_send<T: Send>() {}
+#[allow(dead_code)]
+fn _assert_evaluate_future_is_send() {
+    assert_send::<EvaluateFuture>();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::task::Wake;
+
+    #[derive(Default)]
+    struct RecordingWaker {
+        woken: AtomicBool,
     }
-    loop {
-        if let Some(waker) = fut.waker.take() {
-            waker.wake();
-            break;
+
+    impl Wake for RecordingWaker {
+        fn wake(self: Arc<Self>) {
+            self.woken.store(true, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn evaluate_callback_wakes_future_polled_before_callback() {
+        let mut fut = EvaluateFuture::new();
+        let recorder = Arc::new(RecordingWaker::default());
+        let waker = Waker::from(recorder.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        assert!(matches!(Pin::new(&mut fut).poll(&mut cx), Poll::Pending));
+        assert!(!recorder.woken.load(Ordering::SeqCst));
+
+        let raw = fut.inner.clone().into_raw();
+        unsafe { evaluate_callback(raw as *const c_void, 1, 0) };
+
+        assert!(recorder.woken.load(Ordering::SeqCst));
+        match Pin::new(&mut fut).poll(&mut cx) {
+            Poll::Ready(Ok(())) => {}
+            other => panic!("expected Ready(Ok(())), got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn evaluate_callback_before_poll_completes_immediately() {
+        let mut fut = EvaluateFuture::new();
+
+        let raw = fut.inner.clone().into_raw();
+        unsafe { evaluate_callback(raw as *const c_void, 0, LAError::LAErrorUserCancel as i32) };
+
+        let recorder = Arc::new(RecordingWaker::default());
+        let waker = Waker::from(recorder.clone());
+        let mut cx = Context::from_waker(&waker);
+        match Pin::new(&mut fut).poll(&mut cx) {
+            Poll::Ready(Err(LAError::LAErrorUserCancel)) => {}
+            other => panic!("expected Ready(Err(LAErrorUserCancel)), got {:?}", other),
         }
-        // the callback usually needs some time to be call (user need time to respond),
-        // during that period the waker should already set.
+    }
+
+    #[test]
+    fn la_biometry_type_unknown_fallback() {
+        assert_eq!(LABiometryType::from_i32(2), Some(LABiometryType::FaceID));
+        assert_eq!(LABiometryType::from_i32(999), None);
+        assert_eq!(
+            LABiometryType::from_i32(999).unwrap_or(LABiometryType::Unknown),
+            LABiometryType::Unknown
+        );
+    }
+
+    #[test]
+    fn la_error_unknown_fallback() {
+        assert!(LAError::from_i32(-1).is_some());
+        assert!(LAError::from_i32(-999).is_none());
+        assert_eq!(
+            LAError::from_i32(-999).unwrap_or(LAError::LAErrorUnknown) as i32,
+            LAError::LAErrorUnknown as i32
+        );
     }
 }